@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use clap::Parser;
 use remotia::pipeline::registry::PipelineRegistry;
 use remotia::profilation::loggers::console::ConsoleAverageStatsLogger;
@@ -18,7 +20,10 @@ use remotia_srt::{
     srt_tokio::{options::ByteCount, SrtSocket},
 };
 
-use paper_experiments_2k23::types::{BufferType::*, Error::*, FrameData, Stat::*};
+use paper_experiments_2k23::control::ControlChannel;
+use paper_experiments_2k23::overlay::StatsOverlay;
+use paper_experiments_2k23::recording::Recorder;
+use paper_experiments_2k23::types::{BufferType::*, ControlMessage, Error::*, FrameData, Stat::*};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -31,8 +36,33 @@ struct Args {
     #[arg(long, default_value_t=String::from("127.0.0.1:9000"))]
     server_address: String,
 
+    /// Address of the sender's control listener, used to request an
+    /// immediate keyframe after a decode failure.
+    #[arg(long, default_value_t=String::from("127.0.0.1:9001"))]
+    control_address: String,
+
     #[arg(long, default_value_t=String::from("h264"))]
     codec_id: String,
+
+    /// Path to a TTF font used to burn the live statistics onto decoded
+    /// frames. When unset, no overlay is drawn.
+    #[arg(long)]
+    stats_overlay_font: Option<String>,
+
+    #[arg(long, default_value_t = 16.0)]
+    stats_overlay_px: f32,
+
+    /// Path (including extension) to mux the incoming encoded stream into,
+    /// e.g. "out.ts". The stream is split into one file per
+    /// `--segment-duration`, named with the segment index before the
+    /// extension ("out.0.ts", "out.1.ts", ...). Muxed as MPEG-TS, which
+    /// (unlike mp4/mov) accepts the raw Annex-B H264 packets coming off the
+    /// wire directly. When unset, nothing is recorded.
+    #[arg(long)]
+    record: Option<String>,
+
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "4s")]
+    segment_duration: Duration,
 }
 
 const POOLS_SIZE: usize = 1;
@@ -61,9 +91,13 @@ async fn main() {
         .register(DecodedRGBAFrameBuffer, POOLS_SIZE, pixels_count * 4)
         .await;
 
+    // Simulcast servers tag each encoded buffer with a variant index; until
+    // adaptive selection lands, the receiver always decodes variant 0.
+    const RECEIVED_VARIANT: u8 = 0;
+
     let (decoder_pusher, decoder_puller) = DecoderBuilder::new()
         .codec_id(&args.codec_id)
-        .encoded_buffer_key(EncodedFrameBuffer)
+        .encoded_buffer_key(EncodedFrameBuffer(RECEIVED_VARIANT))
         .decoded_buffer_key(DecodedRGBAFrameBuffer)
         .scaler(
             ScalerBuilder::new()
@@ -77,6 +111,21 @@ async fn main() {
         .codec_error(CodecError)
         .build();
 
+    log::info!("Connecting to the control channel...");
+    let control_socket = SrtSocket::builder()
+        .call(args.control_address.as_str(), None)
+        .await
+        .unwrap();
+    let (control_tx, mut control_rx) = tokio::sync::mpsc::unbounded_channel::<ControlMessage>();
+    tokio::spawn(async move {
+        let mut control_channel = ControlChannel::new(control_socket);
+        while let Some(message) = control_rx.recv().await {
+            if let Err(err) = control_channel.send(message).await {
+                log::warn!("Failed to send control message: {:?}", err);
+            }
+        }
+    });
+
     let mut pipelines = PipelineRegistry::<FrameData, Pipelines>::new();
 
     register!(
@@ -84,8 +133,9 @@ async fn main() {
         Pipelines::Error,
         Pipeline::<FrameData>::singleton(
             Component::new()
-                .append(Function::new(|fd| {
-                    log::warn!("Dropped frame");
+                .append(Function::new(move |fd| {
+                    log::warn!("Dropped frame, requesting a keyframe");
+                    let _ = control_tx.send(ControlMessage::ForceKeyframe(RECEIVED_VARIANT));
                     Some(fd)
                 }))
                 .append(pools.get(SerializedFrameData).redeemer().soft())
@@ -105,31 +155,52 @@ async fn main() {
         pipelines,
         Pipelines::Main,
         Pipeline::<FrameData>::new()
-            .link(
-                Component::new()
+            .link({
+                let mut reception_component = Component::new()
                     .append(pools.get(SerializedFrameData).borrower())
                     .append(SRTFrameReceiver::new(SerializedFrameData, socket))
                     .append(BincodeDeserializer::new(SerializedFrameData))
                     .append(TimestampDiffCalculator::new(CaptureTime, ReceptionDelay))
                     .append(pools.get(SerializedFrameData).redeemer())
-                    .append(TimestampAdder::new(DecodePushTime))
+                    .append(TimestampAdder::new(DecodePushTime));
+                if let Some(record_path) = &args.record {
+                    reception_component = reception_component.append(Recorder::new(
+                        EncodedFrameBuffer(RECEIVED_VARIANT),
+                        record_path,
+                        "mpegts",
+                        args.width as i32,
+                        args.height as i32,
+                        args.segment_duration,
+                    ));
+                }
+                reception_component
                     .append(decoder_pusher)
-                    .append(OnErrorSwitch::new(pipelines.get_mut(&Pipelines::Error))),
-            )
-            .link(
-                Component::new()
+                    .append(OnErrorSwitch::new(pipelines.get_mut(&Pipelines::Error)))
+            })
+            .link({
+                let mut render_component = Component::new()
                     .append(pools.get(DecodedRGBAFrameBuffer).borrower())
                     .append(decoder_puller)
                     .append(OnErrorSwitch::new(pipelines.get_mut(&Pipelines::Error)))
-                    .append(TimestampDiffCalculator::new(DecodePushTime, DecodeTime))
+                    .append(TimestampDiffCalculator::new(DecodePushTime, DecodeTime));
+                if let Some(font_path) = &args.stats_overlay_font {
+                    render_component = render_component.append(StatsOverlay::new(
+                        DecodedRGBAFrameBuffer,
+                        args.width,
+                        args.height,
+                        font_path,
+                        args.stats_overlay_px,
+                    ));
+                }
+                render_component
                     .append(WinitRenderer::new(
                         DecodedRGBAFrameBuffer,
                         args.width,
                         args.height,
                     ))
                     .append(TimestampDiffCalculator::new(CaptureTime, FrameDelay))
-                    .append(pools.get(DecodedRGBAFrameBuffer).redeemer()),
-            )
+                    .append(pools.get(DecodedRGBAFrameBuffer).redeemer())
+            })
             .link(
                 Component::new().append(
                     ConsoleAverageStatsLogger::new()
@@ -1,7 +1,17 @@
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use clap::Parser;
-use paper_experiments_2k23::types::{BufferType::*, FrameData, Stat::*};
+use clap::{Parser, ValueEnum};
+use paper_experiments_2k23::capture::TestPatternCapturer;
+use paper_experiments_2k23::control::ControlChannel;
+use paper_experiments_2k23::keyframe::ForceKeyframeFiller;
+use paper_experiments_2k23::timing::{PresentationTimestampFiller, PresentationTimestamper};
+use paper_experiments_2k23::types::{BufferType::*, ControlMessage, FrameData, Stat::*};
 use remotia::capture::y4m::Y4MFrameCapturer;
 use remotia::profilation::loggers::console::ConsoleAverageStatsLogger;
 use remotia::profilation::time::diff::TimestampDiffCalculator;
@@ -23,10 +33,20 @@ use remotia_srt::{
 
 use remotia::register;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Source {
+    Y4m,
+    Testpattern,
+}
+
 #[derive(Parser, Debug)]
 struct Args {
+    /// Required when `--source y4m` (the default).
     #[arg(long)]
-    file_path: String,
+    file_path: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = Source::Y4m)]
+    source: Source,
 
     #[arg(short, long, default_value_t = 60)]
     framerate: u64,
@@ -34,6 +54,11 @@ struct Args {
     #[arg(long, default_value_t=String::from(":9000"))]
     listen_address: String,
 
+    /// Address the receiver connects back on to request an immediate
+    /// keyframe after a decode failure.
+    #[arg(long, default_value_t=String::from(":9001"))]
+    control_listen_address: String,
+
     #[arg(long, default_value_t=String::from("libx264"))]
     codec_id: String,
 
@@ -45,6 +70,47 @@ struct Args {
 
     #[arg(id = "codec-option", long)]
     codec_options: Vec<String>,
+
+    /// Quality variant to encode in parallel, formatted as
+    /// "<width>x<height>@<bitrate> <codec_id>" (e.g. "640x360@800k libx264").
+    /// Can be repeated. When given, only the listed variants are encoded.
+    /// When omitted, a single variant is built from
+    /// `--width`/`--height`/`--codec-id`/`--codec-option`.
+    #[arg(long = "variant")]
+    variants: Vec<String>,
+}
+
+struct VariantSpec {
+    width: u32,
+    height: u32,
+    bitrate: String,
+    codec_id: String,
+}
+
+impl VariantSpec {
+    fn parse(raw: &str) -> Self {
+        let mut fragments = raw.split_whitespace();
+        let dims_and_bitrate = fragments
+            .next()
+            .expect("variant is missing its <width>x<height>@<bitrate> part");
+        let codec_id = fragments
+            .next()
+            .expect("variant is missing its codec id")
+            .to_string();
+        let (dims, bitrate) = dims_and_bitrate
+            .split_once('@')
+            .expect("variant dimensions must be formatted as <width>x<height>@<bitrate>");
+        let (width, height) = dims
+            .split_once('x')
+            .expect("variant dimensions must be formatted as <width>x<height>");
+
+        Self {
+            width: width.parse().expect("invalid variant width"),
+            height: height.parse().expect("invalid variant height"),
+            bitrate: bitrate.to_string(),
+            codec_id,
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -64,57 +130,129 @@ async fn main() {
     let width = args.width;
     let height = args.height;
 
+    let variants: Vec<VariantSpec> = if args.variants.is_empty() {
+        vec![VariantSpec {
+            width,
+            height,
+            bitrate: String::new(),
+            codec_id: args.codec_id.clone(),
+        }]
+    } else {
+        args.variants.iter().map(|raw| VariantSpec::parse(raw)).collect()
+    };
+    log::info!("Encoding {} variant(s)", variants.len());
+
     let mut pools = PoolRegistry::new();
     let pixels_count = (width * height) as usize;
     pools
         .register(YUVFrameBuffer, POOLS_SIZE, pixels_count * 4)
         .await;
-    pools
-        .register(EncodedFrameBuffer, POOLS_SIZE, pixels_count * 4)
-        .await;
     pools
         .register(SerializedFrameData, POOLS_SIZE, pixels_count * 4)
         .await;
 
-    log::info!("{:?}", args.codec_options);
-    let mut options = Options::new();
-    for option in args.codec_options {
-        let mut fragments = option.split(" ");
-        let (key, value) = (fragments.next().unwrap(), fragments.next().unwrap());
-        options = options.set(key, value);
+    let mut encoder_pushers = Vec::new();
+    let mut encoder_pullers = Vec::new();
+    for (index, variant) in variants.iter().enumerate() {
+        let variant_index = index as u8;
+        pools
+            .register(
+                EncodedFrameBuffer(variant_index),
+                POOLS_SIZE,
+                (variant.width * variant.height) as usize * 4,
+            )
+            .await;
+
+        log::info!(
+            "Variant {}: {}x{} {} {:?}",
+            variant_index,
+            variant.width,
+            variant.height,
+            variant.codec_id,
+            args.codec_options
+        );
+        let mut options = Options::new();
+        for option in &args.codec_options {
+            let mut fragments = option.split(" ");
+            let (key, value) = (fragments.next().unwrap(), fragments.next().unwrap());
+            options = options.set(key, value);
+        }
+        if !variant.bitrate.is_empty() {
+            options = options.set("b", &variant.bitrate);
+        }
+
+        let (encoder_pusher, encoder_puller) = EncoderBuilder::new()
+            .codec_id(&variant.codec_id)
+            .filler(PresentationTimestampFiller::new(ForceKeyframeFiller::new(
+                YUV420PFrameFiller::new(YUVFrameBuffer),
+                variant_index,
+            )))
+            .encoded_buffer_key(EncodedFrameBuffer(variant_index))
+            .scaler(
+                ScalerBuilder::new()
+                    .input_width(width as i32)
+                    .input_height(height as i32)
+                    .output_width(variant.width as i32)
+                    .output_height(variant.height as i32)
+                    .input_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_YUV420P)
+                    // .input_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_RGBA)
+                    .output_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_YUV420P)
+                    .build(),
+            )
+            .options(options)
+            .build();
+
+        encoder_pushers.push(encoder_pusher);
+        encoder_pullers.push(encoder_puller);
     }
-    let (encoder_pusher, encoder_puller) = EncoderBuilder::new()
-        .codec_id(&args.codec_id)
-        .filler(YUV420PFrameFiller::new(YUVFrameBuffer))
-        .encoded_buffer_key(EncodedFrameBuffer)
-        .scaler(
-            ScalerBuilder::new()
-                .input_width(width as i32)
-                .input_height(height as i32)
-                .output_width(width as i32)
-                .output_height(height as i32)
-                .input_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_YUV420P)
-                // .input_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_RGBA)
-                .output_pixel_format(ffi::AVPixelFormat_AV_PIX_FMT_YUV420P)
-                .build(),
-        )
-        .options(options)
-        .build();
+    let variants_count = variants.len() as u8;
+
+    let force_keyframe_flags: Arc<Vec<AtomicBool>> = Arc::new(
+        (0..variants_count)
+            .map(|_| AtomicBool::new(false))
+            .collect(),
+    );
+
+    log::info!("Waiting for a control connection...");
+    let control_socket = SrtSocket::builder()
+        .listen_on(args.control_listen_address.as_str())
+        .await
+        .unwrap();
+    tokio::spawn({
+        let force_keyframe_flags = force_keyframe_flags.clone();
+        let mut control_channel = ControlChannel::new(control_socket);
+        async move {
+            while let Some(message) = control_channel.recv().await {
+                match message {
+                    ControlMessage::ForceKeyframe(variant_index) => {
+                        log::info!("Forcing a keyframe on variant {}", variant_index);
+                        if let Some(flag) = force_keyframe_flags.get(variant_index as usize) {
+                            flag.store(true, Ordering::SeqCst);
+                        }
+                    }
+                }
+            }
+        }
+    });
 
     let mut pipelines = PipelineRegistry::<FrameData, Pipelines>::new();
 
     register!(
         pipelines,
         Pipelines::Error,
-        Pipeline::<FrameData>::singleton(
-            Component::new()
+        Pipeline::<FrameData>::singleton({
+            let mut error_component = Component::new()
                 .append(Function::new(|fd| {
                     log::warn!("Dropped frame");
                     Some(fd)
                 }))
-                .append(pools.get(YUVFrameBuffer).redeemer().soft())
-                .append(pools.get(EncodedFrameBuffer).redeemer().soft()),
-        )
+                .append(pools.get(YUVFrameBuffer).redeemer().soft());
+            for variant_index in 0..variants_count {
+                error_component = error_component
+                    .append(pools.get(EncodedFrameBuffer(variant_index)).redeemer().soft());
+            }
+            error_component
+        })
         .feedable()
     );
 
@@ -130,44 +268,84 @@ async fn main() {
         pipelines,
         Pipelines::Main,
         Pipeline::<FrameData>::new()
-            .link(
-                Component::new()
+            .link({
+                let mut capture_component = Component::new()
                     .append(Ticker::new(1000 / args.framerate))
                     .append(pools.get(YUVFrameBuffer).borrower())
                     .append(TimestampAdder::new(CaptureTime))
-                    .append(Y4MFrameCapturer::new(YUVFrameBuffer, &args.file_path))
-                    .append(TimestampAdder::new(EncodePushTime))
-                    .append(encoder_pusher),
-            )
-            .link(
-                Component::new()
-                    .append(pools.get(YUVFrameBuffer).redeemer())
-                    .append(pools.get(EncodedFrameBuffer).borrower())
-                    .append(encoder_puller)
-                    .append(TimestampDiffCalculator::new(EncodePushTime, EncodeTime))
-                    .append(OnErrorSwitch::new(pipelines.get_mut(&Pipelines::Error))),
-            )
-            .link(
-                Component::new()
+                    .append(PresentationTimestamper::new(args.framerate));
+                capture_component = match args.source {
+                    Source::Y4m => {
+                        let file_path = args
+                            .file_path
+                            .as_deref()
+                            .expect("--file-path is required when --source y4m");
+                        capture_component.append(Y4MFrameCapturer::new(YUVFrameBuffer, file_path))
+                    }
+                    Source::Testpattern => {
+                        capture_component.append(TestPatternCapturer::new(YUVFrameBuffer, width, height))
+                    }
+                };
+                capture_component = capture_component.append(TimestampAdder::new(EncodePushTime));
+                for (variant_index, encoder_pusher) in encoder_pushers.into_iter().enumerate() {
+                    let variant_index = variant_index as u8;
+                    let force_keyframe_flags = force_keyframe_flags.clone();
+                    capture_component = capture_component
+                        .append(Function::new(move |mut fd| {
+                            if force_keyframe_flags[variant_index as usize].swap(false, Ordering::SeqCst)
+                            {
+                                fd.set(ForceKeyframe(variant_index), 1);
+                                // The encoder honors ForceKeyframe via
+                                // ForceKeyframeFiller, so this packet is known
+                                // to be a keyframe once it comes out the other
+                                // side — tag it for a muxing receiver.
+                                fd.set(Keyframe(variant_index), 1);
+                            }
+                            Some(fd)
+                        }))
+                        .append(encoder_pusher);
+                }
+                capture_component
+            })
+            .link({
+                let mut encode_component = Component::new().append(pools.get(YUVFrameBuffer).redeemer());
+                for (variant_index, encoder_puller) in encoder_pullers.into_iter().enumerate() {
+                    let variant_index = variant_index as u8;
+                    encode_component = encode_component
+                        .append(pools.get(EncodedFrameBuffer(variant_index)).borrower())
+                        .append(encoder_puller)
+                        .append(TimestampDiffCalculator::new(
+                            EncodePushTime,
+                            EncodeTime(variant_index),
+                        ))
+                        .append(OnErrorSwitch::new(pipelines.get_mut(&Pipelines::Error)));
+                }
+                encode_component
+            })
+            .link({
+                let mut transmission_component = Component::new()
                     .append(TimestampAdder::new(TransmissionStartTime))
                     .append(pools.get(SerializedFrameData).borrower())
-                    .append(BincodeSerializer::new(SerializedFrameData))
-                    .append(pools.get(EncodedFrameBuffer).redeemer())
+                    .append(BincodeSerializer::new(SerializedFrameData));
+                for variant_index in 0..variants_count {
+                    transmission_component = transmission_component
+                        .append(pools.get(EncodedFrameBuffer(variant_index)).redeemer());
+                }
+                transmission_component
                     .append(SRTFrameSender::new(SerializedFrameData, socket))
                     .append(TimestampDiffCalculator::new(
                         TransmissionStartTime,
                         TransmissionTime,
                     ))
-                    .append(pools.get(SerializedFrameData).redeemer()),
-            )
-            .link(
-                Component::new().append(
-                    ConsoleAverageStatsLogger::new()
-                        .header("Statistics")
-                        .log(EncodeTime)
-                        .log(TransmissionTime),
-                ),
-            )
+                    .append(pools.get(SerializedFrameData).redeemer())
+            })
+            .link({
+                let mut logger = ConsoleAverageStatsLogger::new().header("Statistics");
+                for variant_index in 0..variants_count {
+                    logger = logger.log(EncodeTime(variant_index));
+                }
+                Component::new().append(logger.log(TransmissionTime))
+            })
     );
 
     pipelines.run().await;
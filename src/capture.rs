@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use remotia::traits::{BorrowMutFrameProperties, Processor};
+
+use crate::types::{BufferType, FrameData};
+
+// Standard SMPTE 75% color bars, as (Y, U, V) tuples for a YUV420p buffer.
+const SMPTE_BARS: [(u8, u8, u8); 7] = [
+    (180, 128, 128), // white
+    (162, 44, 142),  // yellow
+    (131, 156, 44),  // cyan
+    (112, 72, 58),   // green
+    (84, 184, 198),  // magenta
+    (65, 100, 212),  // red
+    (35, 212, 114),  // blue
+];
+
+/// Fills a YUV420p buffer with a scrolling SMPTE color bar pattern instead
+/// of reading from a y4m file, so the encode/transport pipeline can be
+/// benchmarked without needing media assets. A drop-in replacement for
+/// `Y4MFrameCapturer` in the capture stage: it leaves `CaptureTime` to the
+/// same `TimestampAdder` the y4m path uses.
+pub struct TestPatternCapturer {
+    buffer_key: BufferType,
+    width: u32,
+    height: u32,
+    frame_index: u64,
+}
+
+impl TestPatternCapturer {
+    pub fn new(buffer_key: BufferType, width: u32, height: u32) -> Self {
+        Self {
+            buffer_key,
+            width,
+            height,
+            frame_index: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl Processor<FrameData> for TestPatternCapturer {
+    async fn process(&mut self, mut frame_data: FrameData) -> Option<FrameData> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let scroll = self.frame_index as usize % width;
+
+        if let Some(buffer) = frame_data.get_mut_ref(&self.buffer_key) {
+            let bar_at = |x: usize| ((x + scroll) % width) * SMPTE_BARS.len() / width;
+
+            for y in 0..height {
+                for x in 0..width {
+                    buffer[y * width + x] = SMPTE_BARS[bar_at(x)].0;
+                }
+            }
+
+            let chroma_width = width / 2;
+            let chroma_height = height / 2;
+            let u_plane_offset = width * height;
+            let v_plane_offset = u_plane_offset + chroma_width * chroma_height;
+            for y in 0..chroma_height {
+                for x in 0..chroma_width {
+                    let (_, u, v) = SMPTE_BARS[bar_at(x * 2)];
+                    buffer[u_plane_offset + y * chroma_width + x] = u;
+                    buffer[v_plane_offset + y * chroma_width + x] = v;
+                }
+            }
+        }
+
+        self.frame_index += 1;
+
+        Some(frame_data)
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Instant;
+
+use bincode::config;
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use remotia_srt::srt_tokio::SrtSocket;
+
+use crate::types::ControlMessage;
+
+/// A small reverse channel carrying `ControlMessage`s over its own SRT
+/// connection, kept separate from the socket used for the frame stream so
+/// control traffic (e.g. "force a keyframe") isn't queued behind frame data.
+pub struct ControlChannel {
+    socket: SrtSocket,
+}
+
+impl ControlChannel {
+    pub fn new(socket: SrtSocket) -> Self {
+        Self { socket }
+    }
+
+    pub async fn send(&mut self, message: ControlMessage) -> anyhow::Result<()> {
+        let bytes = bincode::encode_to_vec(message, config::standard())?;
+        self.socket.send((Instant::now(), Bytes::from(bytes))).await?;
+        Ok(())
+    }
+
+    pub async fn recv(&mut self) -> Option<ControlMessage> {
+        let (_, bytes) = self.socket.next().await?.ok()?;
+        bincode::decode_from_slice(&bytes, config::standard())
+            .ok()
+            .map(|(message, _)| message)
+    }
+}
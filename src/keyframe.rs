@@ -0,0 +1,38 @@
+use remotia::traits::FrameProperties;
+use remotia_ffmpeg_codecs::{encoders::fillers::FrameFiller, ffi};
+
+use crate::types::{FrameData, Stat};
+
+/// Wraps another `FrameFiller` and, once it has filled the `AVFrame`, forces
+/// an IDR on it whenever the frame carries a `Stat::ForceKeyframe` flag for
+/// `variant_index` — the same way an encoder would honor a
+/// `AV_PICTURE_TYPE_I` override on input. This is what actually turns a
+/// `ForceKeyframe` stat into a keyframe on the wire; without it the stat is
+/// just inert metadata the encoder never looks at.
+pub struct ForceKeyframeFiller<Inner> {
+    inner: Inner,
+    variant_index: u8,
+}
+
+impl<Inner> ForceKeyframeFiller<Inner> {
+    pub fn new(inner: Inner, variant_index: u8) -> Self {
+        Self {
+            inner,
+            variant_index,
+        }
+    }
+}
+
+impl<Inner: FrameFiller<FrameData>> FrameFiller<FrameData> for ForceKeyframeFiller<Inner> {
+    fn fill(&self, frame_data: &FrameData, frame: &mut ffi::AVFrame) {
+        self.inner.fill(frame_data, frame);
+
+        let forced = FrameProperties::get(frame_data, &Stat::ForceKeyframe(self.variant_index))
+            .unwrap_or(0)
+            != 0;
+        if forced {
+            frame.pict_type = ffi::AVPictureType_AV_PICTURE_TYPE_I;
+            frame.key_frame = 1;
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod capture;
+pub mod control;
+pub mod keyframe;
+pub mod overlay;
+pub mod recording;
+pub mod timing;
+pub mod types;
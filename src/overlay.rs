@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use fontdue::{Font, FontSettings, Metrics};
+use remotia::traits::{BorrowMutFrameProperties, FrameProperties, Processor};
+
+use crate::types::{BufferType, FrameData, Stat};
+
+const GLYPH_COLOR: (u8, u8, u8) = (255, 255, 255);
+
+/// Burns the live `Stat`s of a frame directly into a BGRA buffer, so
+/// recordings/screenshots taken downstream (e.g. after `WinitRenderer`)
+/// carry the telemetry without needing a separate GUI. Glyphs are
+/// rasterized once per (character, px size) and cached, since re-rasterizing
+/// every frame would dominate the cost of drawing a handful of lines.
+pub struct StatsOverlay {
+    buffer_key: BufferType,
+    width: u32,
+    height: u32,
+    font: Font,
+    px: f32,
+    origin_x: u32,
+    origin_y: u32,
+    glyph_cache: HashMap<char, (Metrics, Vec<u8>)>,
+}
+
+impl StatsOverlay {
+    pub fn new(buffer_key: BufferType, width: u32, height: u32, font_path: &str, px: f32) -> Self {
+        let font_bytes = std::fs::read(font_path)
+            .unwrap_or_else(|error| panic!("failed to read overlay font '{}': {}", font_path, error));
+        let font = Font::from_bytes(font_bytes, FontSettings::default())
+            .expect("failed to parse overlay font");
+
+        Self {
+            buffer_key,
+            width,
+            height,
+            font,
+            px,
+            origin_x: 8,
+            origin_y: 8,
+            glyph_cache: HashMap::new(),
+        }
+    }
+
+    fn glyph(&mut self, character: char) -> &(Metrics, Vec<u8>) {
+        self.glyph_cache
+            .entry(character)
+            .or_insert_with(|| self.font.rasterize(character, self.px))
+    }
+
+    fn draw_line(&mut self, buffer: &mut [u8], line_origin_x: u32, line_origin_y: u32, text: &str) {
+        let (width, height) = (self.width, self.height);
+        let mut cursor_x = line_origin_x;
+        for character in text.chars() {
+            let (metrics, coverage) = self.glyph(character);
+
+            for gy in 0..metrics.height {
+                for gx in 0..metrics.width {
+                    let alpha = coverage[gy * metrics.width + gx];
+                    if alpha == 0 {
+                        continue;
+                    }
+
+                    let x = cursor_x as i64 + gx as i64 + metrics.xmin as i64;
+                    let y = line_origin_y as i64 + gy as i64 - metrics.ymin as i64;
+                    if x < 0 || y < 0 || x as u32 >= width || y as u32 >= height {
+                        continue;
+                    }
+
+                    blend_pixel(buffer, width, x as u32, y as u32, alpha, GLYPH_COLOR);
+                }
+            }
+
+            cursor_x += metrics.advance_width.round() as u32;
+        }
+    }
+}
+
+fn blend_pixel(buffer: &mut [u8], width: u32, x: u32, y: u32, coverage: u8, color: (u8, u8, u8)) {
+    let pixel_index = (y * width + x) as usize * 4;
+    if pixel_index + 4 > buffer.len() {
+        return;
+    }
+
+    let alpha = coverage as u32;
+    let blend = |src: u8, dst: u8| -> u8 {
+        ((src as u32 * alpha + dst as u32 * (255 - alpha)) / 255) as u8
+    };
+
+    // BGRA byte order, matching the decoder's output pixel format.
+    buffer[pixel_index] = blend(color.2, buffer[pixel_index]);
+    buffer[pixel_index + 1] = blend(color.1, buffer[pixel_index + 1]);
+    buffer[pixel_index + 2] = blend(color.0, buffer[pixel_index + 2]);
+}
+
+#[async_trait]
+impl Processor<FrameData> for StatsOverlay {
+    async fn process(&mut self, mut frame_data: FrameData) -> Option<FrameData> {
+        let lines = [
+            ("Reception delay", Stat::ReceptionDelay),
+            ("Frame delay", Stat::FrameDelay),
+            ("Decode time", Stat::DecodeTime),
+        ]
+        .into_iter()
+        .filter_map(|(label, stat)| {
+            FrameProperties::get(&frame_data, &stat).map(|value| format!("{}: {} ms", label, value))
+        })
+        .collect::<Vec<_>>();
+
+        let line_height = (self.px * 1.2).round() as u32;
+        let (origin_x, origin_y) = (self.origin_x, self.origin_y);
+        if let Some(buffer) = frame_data.get_mut_ref(&self.buffer_key) {
+            for (index, line) in lines.iter().enumerate() {
+                let line_origin_y = origin_y + index as u32 * line_height;
+                self.draw_line(&mut buffer[..], origin_x, line_origin_y, line);
+            }
+        }
+
+        Some(frame_data)
+    }
+}
@@ -0,0 +1,260 @@
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Write;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use remotia::traits::BorrowFrameProperties;
+use remotia::traits::{FrameProperties, Processor};
+use remotia_ffmpeg_codecs::ffi;
+
+use crate::timing::TIME_BASE_DEN;
+use crate::types::{BufferType, FrameData, Stat};
+
+const AVIO_BUFFER_SIZE: usize = 4096;
+
+/// Muxes raw encoded packets into a container file through a custom AVIO
+/// context backed by a plain `File`, instead of handing libavformat a path.
+/// Follows the alloc/flush/free lifecycle FFmpeg's custom-AVIO examples
+/// require: the `Drop` impl below writes the trailer, flushes the AVIO
+/// buffer, then frees the buffer and the context separately —
+/// `avio_context_free` only frees the `AVIOContext` struct itself, not the
+/// `av_malloc`'d buffer backing it, so that buffer needs its own `av_freep`.
+/// Only muxes into formats that accept raw Annex-B H264 packets without a
+/// bitstream filter (e.g. `mpegts`) — mov/mp4 need AVCC framing plus SPS/PPS
+/// extradata that this muxer never produces.
+struct ContainerMuxer {
+    format_context: *mut ffi::AVFormatContext,
+    avio_context: *mut ffi::AVIOContext,
+    sink: *mut File,
+    stream_index: c_int,
+    last_pts: Option<i64>,
+    // Only set once `avformat_write_header` actually succeeds, so `Drop`
+    // doesn't write a trailer for a header that was never written.
+    header_written: bool,
+}
+
+unsafe impl Send for ContainerMuxer {}
+
+impl ContainerMuxer {
+    fn new(output_path: &str, format_name: &str, width: i32, height: i32) -> Self {
+        unsafe {
+            let sink = Box::into_raw(Box::new(
+                File::create(output_path).expect("failed to create recording output file"),
+            ));
+
+            let avio_buffer = ffi::av_malloc(AVIO_BUFFER_SIZE) as *mut u8;
+            let avio_context = ffi::avio_alloc_context(
+                avio_buffer,
+                AVIO_BUFFER_SIZE as c_int,
+                1,
+                sink as *mut c_void,
+                None,
+                Some(write_packet),
+                None,
+            );
+
+            let mut format_context: *mut ffi::AVFormatContext = ptr::null_mut();
+            let format_name_c = CString::new(format_name).expect("format name must not contain NUL");
+            let alloc_result = ffi::avformat_alloc_output_context2(
+                &mut format_context,
+                ptr::null_mut(),
+                format_name_c.as_ptr(),
+                ptr::null(),
+            );
+            assert!(
+                alloc_result >= 0 && !format_context.is_null(),
+                "failed to allocate an output context for format '{}' ({})",
+                format_name,
+                alloc_result
+            );
+            (*format_context).pb = avio_context;
+            (*format_context).flags |= ffi::AVFMT_FLAG_CUSTOM_IO as c_int;
+
+            let stream = ffi::avformat_new_stream(format_context, ptr::null());
+            let stream_index = (*stream).index;
+            // Must match the units `Stat::PresentationTimestamp`/
+            // `Stat::DecodeTimestamp` are expressed in, or libavformat
+            // rescales every timestamp from an unset `{0, 1}` time_base.
+            (*stream).time_base = ffi::AVRational {
+                num: 1,
+                den: TIME_BASE_DEN as c_int,
+            };
+            (*(*stream).codecpar).codec_type = ffi::AVMediaType_AVMEDIA_TYPE_VIDEO;
+            (*(*stream).codecpar).codec_id = ffi::AVCodecID_AV_CODEC_ID_H264;
+            (*(*stream).codecpar).width = width;
+            (*(*stream).codecpar).height = height;
+
+            let header_result = ffi::avformat_write_header(format_context, ptr::null_mut());
+            let header_written = header_result >= 0;
+            if !header_written {
+                log::error!(
+                    "Failed to write container header for '{}' ({})",
+                    output_path,
+                    header_result
+                );
+            }
+
+            Self {
+                format_context,
+                avio_context,
+                sink,
+                stream_index,
+                last_pts: None,
+                header_written,
+            }
+        }
+    }
+
+    // `pts`/`dts` come from the stream's own `Stat::PresentationTimestamp`/
+    // `Stat::DecodeTimestamp`, not a locally-incremented counter, so a
+    // muxed recording reflects gaps from dropped frames instead of papering
+    // over them. Guards against a non-monotonic PTS (e.g. a reordered or
+    // duplicate packet) since libavformat rejects those outright.
+    fn write_packet(&mut self, data: &mut [u8], pts: i64, dts: i64, is_keyframe: bool) {
+        if !self.header_written {
+            return;
+        }
+        if matches!(self.last_pts, Some(last_pts) if pts <= last_pts) {
+            log::warn!("Dropping out-of-order packet (pts {})", pts);
+            return;
+        }
+        self.last_pts = Some(pts);
+
+        unsafe {
+            let mut packet: ffi::AVPacket = std::mem::zeroed();
+            packet.data = data.as_mut_ptr();
+            packet.size = data.len() as c_int;
+            packet.stream_index = self.stream_index;
+            packet.pts = pts;
+            packet.dts = dts;
+            if is_keyframe {
+                packet.flags |= ffi::AV_PKT_FLAG_KEY as c_int;
+            }
+
+            let write_result = ffi::av_interleaved_write_frame(self.format_context, &mut packet);
+            if write_result < 0 {
+                log::error!("Failed to mux packet (pts {}): {}", pts, write_result);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn write_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let file = &mut *(opaque as *mut File);
+    let slice = std::slice::from_raw_parts(buf, buf_size as usize);
+    match file.write_all(slice) {
+        Ok(()) => buf_size,
+        Err(_) => ffi::AVERROR_EIO as c_int,
+    }
+}
+
+impl Drop for ContainerMuxer {
+    fn drop(&mut self) {
+        unsafe {
+            if self.header_written {
+                ffi::av_write_trailer(self.format_context);
+                ffi::avio_flush(self.avio_context);
+            }
+            ffi::avformat_free_context(self.format_context);
+            // FFmpeg may have reallocated this buffer since `avio_alloc_context`,
+            // so free the context's current pointer rather than the one we
+            // originally passed in.
+            ffi::av_freep(&mut (*self.avio_context).buffer as *mut _ as *mut c_void);
+            ffi::avio_context_free(&mut self.avio_context);
+            drop(Box::from_raw(self.sink));
+        }
+    }
+}
+
+fn variant_index(buffer_key: BufferType) -> u8 {
+    match buffer_key {
+        BufferType::EncodedFrameBuffer(variant_index) => variant_index,
+        _ => 0,
+    }
+}
+
+/// Receiver-side processor that takes the encoded packet of one variant
+/// (`buffer_key`) before decode and muxes it into a container file, rotating to a new segment
+/// file every `segment_duration`. Reads the buffer through
+/// `BorrowFrameProperties` rather than pulling it, so decode downstream
+/// still gets the packet. `format_name` must accept raw Annex-B H264
+/// packets without a bitstream filter — `mpegts`, not `mp4`/`mov`.
+pub struct Recorder {
+    buffer_key: BufferType,
+    output_path_template: String,
+    format_name: String,
+    width: i32,
+    height: i32,
+    segment_duration: Duration,
+    segment_started_at: Instant,
+    segment_index: u32,
+    muxer: ContainerMuxer,
+}
+
+impl Recorder {
+    pub fn new(
+        buffer_key: BufferType,
+        output_path_template: &str,
+        format_name: &str,
+        width: i32,
+        height: i32,
+        segment_duration: Duration,
+    ) -> Self {
+        let muxer = ContainerMuxer::new(
+            &segment_path(output_path_template, 0),
+            format_name,
+            width,
+            height,
+        );
+
+        Self {
+            buffer_key,
+            output_path_template: output_path_template.to_string(),
+            format_name: format_name.to_string(),
+            width,
+            height,
+            segment_duration,
+            segment_started_at: Instant::now(),
+            segment_index: 0,
+            muxer,
+        }
+    }
+}
+
+fn segment_path(template: &str, segment_index: u32) -> String {
+    match template.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, segment_index, extension),
+        None => format!("{}.{}", template, segment_index),
+    }
+}
+
+#[async_trait]
+impl Processor<FrameData> for Recorder {
+    async fn process(&mut self, frame_data: FrameData) -> Option<FrameData> {
+        if self.segment_started_at.elapsed() >= self.segment_duration {
+            self.segment_index += 1;
+            self.muxer = ContainerMuxer::new(
+                &segment_path(&self.output_path_template, self.segment_index),
+                &self.format_name,
+                self.width,
+                self.height,
+            );
+            self.segment_started_at = Instant::now();
+        }
+
+        if let Some(buffer) = BorrowFrameProperties::get_ref(&frame_data, &self.buffer_key) {
+            let pts = FrameProperties::get(&frame_data, &Stat::PresentationTimestamp).unwrap_or(0) as i64;
+            let dts = FrameProperties::get(&frame_data, &Stat::DecodeTimestamp).unwrap_or(pts as u128) as i64;
+            let is_keyframe = FrameProperties::get(&frame_data, &Stat::Keyframe(variant_index(self.buffer_key)))
+                .unwrap_or(0)
+                != 0;
+            let mut bytes = buffer.to_vec();
+            self.muxer.write_packet(&mut bytes, pts, dts, is_keyframe);
+        }
+
+        Some(frame_data)
+    }
+}
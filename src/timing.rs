@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use remotia::traits::{FrameProperties, Processor};
+use remotia_ffmpeg_codecs::{encoders::fillers::FrameFiller, ffi};
+
+use crate::types::{FrameData, Stat};
+
+/// Time base the encoded stream's PTS/DTS are expressed in, matching the
+/// common MPEG time base rather than the capture framerate, so timestamps
+/// stay meaningful even if the framerate changes mid-stream. Shared with
+/// `recording`, which must declare the same time_base on the muxed stream
+/// for these values to mean anything to libavformat.
+pub(crate) const TIME_BASE_DEN: u64 = 90_000;
+
+/// Assigns `Stat::FrameSequence`/`Stat::PresentationTimestamp`/
+/// `Stat::DecodeTimestamp` at capture time. `FrameSequence` is the raw,
+/// always-increments-by-one capture index, which a receiver can use to
+/// detect gaps.
+/// `PresentationTimestamp`/`DecodeTimestamp` are that same index scaled into
+/// `TIME_BASE_DEN` ticks per second, i.e. multiplied by
+/// `TIME_BASE_DEN / framerate` ticks per frame, so they're expressed in the
+/// stream's actual time_base rather than being a bare frame count. These
+/// stats live on the `FrameData` itself, so they ride along through encoding
+/// and SRT serialization for free and reach the receiver without any extra
+/// wiring. DTS is set equal to PTS and is not updated if an encoder reorders
+/// frames (e.g. B-frames).
+pub struct PresentationTimestamper {
+    next_sequence: u128,
+    ticks_per_frame: u128,
+}
+
+impl PresentationTimestamper {
+    pub fn new(framerate: u64) -> Self {
+        Self {
+            next_sequence: 0,
+            ticks_per_frame: (TIME_BASE_DEN / framerate) as u128,
+        }
+    }
+}
+
+#[async_trait]
+impl Processor<FrameData> for PresentationTimestamper {
+    async fn process(&mut self, mut frame_data: FrameData) -> Option<FrameData> {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let pts = sequence * self.ticks_per_frame;
+        frame_data.set(Stat::FrameSequence, sequence);
+        frame_data.set(Stat::PresentationTimestamp, pts);
+        frame_data.set(Stat::DecodeTimestamp, pts);
+
+        Some(frame_data)
+    }
+}
+
+/// Wraps another `FrameFiller` and, once it has filled the `AVFrame`, stamps
+/// `AVFrame.pts` from `Stat::PresentationTimestamp` — without this the
+/// timestamp `PresentationTimestamper` computes only ever lives on the
+/// `FrameData` side and the encoder assigns its own PTS instead.
+pub struct PresentationTimestampFiller<Inner> {
+    inner: Inner,
+}
+
+impl<Inner> PresentationTimestampFiller<Inner> {
+    pub fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl<Inner: FrameFiller<FrameData>> FrameFiller<FrameData> for PresentationTimestampFiller<Inner> {
+    fn fill(&self, frame_data: &FrameData, frame: &mut ffi::AVFrame) {
+        self.inner.fill(frame_data, frame);
+
+        if let Some(pts) = FrameProperties::get(frame_data, &Stat::PresentationTimestamp) {
+            frame.pts = pts as i64;
+        }
+    }
+}
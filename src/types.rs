@@ -17,7 +17,9 @@ use remotia::{
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
 pub enum BufferType {
     YUVFrameBuffer,
-    EncodedFrameBuffer,
+    // Parameterized by variant index, so a single FrameData can carry one
+    // encoded buffer per simulcast variant.
+    EncodedFrameBuffer(u8),
     SerializedFrameData,
 
     DecodedRGBAFrameBuffer,
@@ -30,12 +32,35 @@ pub enum Stat {
     TransmissionStartTime,
     DecodePushTime,
 
-    EncodeTime,
+    // Parameterized by variant index (see `BufferType::EncodedFrameBuffer`).
+    EncodeTime(u8),
     TransmissionTime,
     DecodeTime,
 
     FrameDelay,
     ReceptionDelay,
+
+    // Set to a nonzero value when the upstream control channel requested an
+    // immediate keyframe for this variant (see `ControlMessage`).
+    ForceKeyframe(u8),
+
+    // Set to a nonzero value when the packet encoded for this variant is a
+    // keyframe, so a muxing receiver can flag the right packets as sync
+    // samples (AV_PKT_FLAG_KEY) instead of marking none of them. Only
+    // tracks keyframes we explicitly forced via `ForceKeyframe`, not ones
+    // the codec emits on its own periodic GOP boundary.
+    Keyframe(u8),
+
+    // Monotonically increasing index of the captured frame, assigned once
+    // at capture and never reset, so a receiver can detect gaps caused by
+    // packet loss.
+    FrameSequence,
+    // Presentation/decode timestamps, expressed in units of the stream's
+    // time_base (1/`timing::TIME_BASE_DEN` seconds per tick). DTS is set
+    // equal to PTS at capture and is not updated if an encoder reorders
+    // frames (e.g. B-frames).
+    PresentationTimestamp,
+    DecodeTimestamp,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Encode, Decode)]
@@ -44,6 +69,14 @@ pub enum Error {
     CodecError,
 }
 
+/// Out-of-band messages exchanged over the reverse control channel, separate
+/// from the per-frame `FrameData` stream. Used by the receiver to ask the
+/// sender for an immediate keyframe after a decode failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum ControlMessage {
+    ForceKeyframe(u8),
+}
+
 #[derive(Default, Debug)]
 pub struct FrameData {
     statistics: HashMap<Stat, u128>,